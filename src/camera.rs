@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use bevy_ggrs::GgrsSet;
+use bevy_inspector_egui::quick::ResourceInspectorPlugin;
+
+use crate::player::Player;
+use crate::GameState;
+
+#[derive(Component)]
+pub struct PlayerCamera;
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct CameraSettings {
+    pub follow_lerp: f32,
+    pub deadzone: Vec2,
+    pub look_ahead: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            follow_lerp: 1.0 / 3.0,
+            deadzone: Vec2::new(24.0, 16.0),
+            look_ahead: 32.0,
+        }
+    }
+}
+
+pub struct CameraPlugin;
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CameraSettings::default());
+        app.register_type::<CameraSettings>();
+        app.add_plugins(ResourceInspectorPlugin::<CameraSettings>::default());
+        app.add_systems(OnEnter(GameState::Game), spawn_camera);
+        // GgrsSet::Advance is where bevy_ggrs runs GgrsSchedule for the frame; ordering after it
+        // (instead of the empty-of-movement-code GameSystemSet::Player) is what actually
+        // guarantees the player's Transform is post-physics by the time this reads it.
+        app.add_systems(Update, follow_player.after(GgrsSet::Advance));
+    }
+}
+
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn((Name::new("Player Camera"), Camera2dBundle::default(), PlayerCamera));
+}
+
+fn follow_player(
+    settings: Res<CameraSettings>,
+    player_query: Query<(&Transform, &Player), Without<PlayerCamera>>,
+    mut camera_query: Query<&mut Transform, With<PlayerCamera>>,
+) {
+    let Ok(mut camera_transform) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    // The camera follows whichever local player spawned first, same as chunk streaming.
+    let Some((player_transform, player)) = player_query.iter().next() else {
+        return;
+    };
+
+    let look_ahead_offset = Vec2::new(player.direction as f32 * settings.look_ahead, 0.0);
+    let target = player_transform.translation.xy() + look_ahead_offset;
+    let camera_pos = camera_transform.translation.xy();
+
+    let delta = target - camera_pos;
+    let deadzoned_delta = Vec2::new(
+        outside_deadzone(delta.x, settings.deadzone.x),
+        outside_deadzone(delta.y, settings.deadzone.y),
+    );
+    let deadzoned_target = camera_pos + deadzoned_delta;
+
+    let new_position = camera_pos.lerp(deadzoned_target, settings.follow_lerp);
+    camera_transform.translation.x = new_position.x;
+    camera_transform.translation.y = new_position.y;
+}
+
+/// How far `delta` sits outside a `size`-radius deadzone around zero; zero while inside it.
+fn outside_deadzone(delta: f32, size: f32) -> f32 {
+    if delta.abs() <= size {
+        0.0
+    } else {
+        delta - size * delta.signum()
+    }
+}
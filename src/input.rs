@@ -0,0 +1,76 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Jump,
+    Noclip,
+}
+
+/// Two keyboard schemes let two players share one keyboard for couch co-op.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Source {
+    KeyboardLeft,
+    KeyboardRight,
+    Gamepad(Gamepad),
+}
+
+#[derive(Resource, Default)]
+pub struct InputState(HashSet<(Source, Key)>);
+
+impl InputState {
+    pub fn pressed(&self, source: Source, key: Key) -> bool {
+        self.0.contains(&(source, key))
+    }
+}
+
+pub struct InputPlugin;
+impl Plugin for InputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputState>();
+        app.add_systems(PreUpdate, update_input_state);
+    }
+}
+
+fn update_input_state(
+    mut input_state: ResMut<InputState>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+) {
+    input_state.0.clear();
+
+    let mut bind = |source: Source, key: Key, pressed: bool| {
+        if pressed {
+            input_state.0.insert((source, key));
+        }
+    };
+
+    bind(Source::KeyboardLeft, Key::Left, keyboard_input.pressed(KeyCode::KeyA));
+    bind(Source::KeyboardLeft, Key::Right, keyboard_input.pressed(KeyCode::KeyD));
+    bind(Source::KeyboardLeft, Key::Up, keyboard_input.pressed(KeyCode::KeyW));
+    bind(Source::KeyboardLeft, Key::Down, keyboard_input.pressed(KeyCode::KeyS));
+    bind(Source::KeyboardLeft, Key::Jump, keyboard_input.pressed(KeyCode::Space));
+    bind(Source::KeyboardLeft, Key::Noclip, keyboard_input.just_pressed(KeyCode::KeyF));
+
+    bind(Source::KeyboardRight, Key::Left, keyboard_input.pressed(KeyCode::ArrowLeft));
+    bind(Source::KeyboardRight, Key::Right, keyboard_input.pressed(KeyCode::ArrowRight));
+    bind(Source::KeyboardRight, Key::Up, keyboard_input.pressed(KeyCode::ArrowUp));
+    bind(Source::KeyboardRight, Key::Down, keyboard_input.pressed(KeyCode::ArrowDown));
+    bind(Source::KeyboardRight, Key::Jump, keyboard_input.pressed(KeyCode::Enter));
+    bind(Source::KeyboardRight, Key::Noclip, keyboard_input.just_pressed(KeyCode::Slash));
+
+    for &gamepad in gamepads.iter() {
+        bind(Source::Gamepad(gamepad), Key::Left, gamepad_buttons.pressed(GamepadButton::new(gamepad, GamepadButtonType::DPadLeft)));
+        bind(Source::Gamepad(gamepad), Key::Right, gamepad_buttons.pressed(GamepadButton::new(gamepad, GamepadButtonType::DPadRight)));
+        bind(Source::Gamepad(gamepad), Key::Up, gamepad_buttons.pressed(GamepadButton::new(gamepad, GamepadButtonType::DPadUp)));
+        bind(Source::Gamepad(gamepad), Key::Down, gamepad_buttons.pressed(GamepadButton::new(gamepad, GamepadButtonType::DPadDown)));
+        bind(Source::Gamepad(gamepad), Key::Jump, gamepad_buttons.pressed(GamepadButton::new(gamepad, GamepadButtonType::South)));
+        bind(Source::Gamepad(gamepad), Key::Noclip, gamepad_buttons.just_pressed(GamepadButton::new(gamepad, GamepadButtonType::North)));
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_ggrs::{ggrs, GgrsApp, GgrsPlugin, GgrsSchedule, LocalInputs, LocalPlayers, ReadInputs};
+use bevy_xpbd_2d::{components::{LinearVelocity, Position}, PhysicsSchedule, SubstepSchedule, SubstepSet};
+use bytemuck::{Pod, Zeroable};
+
+use crate::input::{InputState, Key, Source};
+use crate::player::{apply_gravity, handle_fall_damage, is_inside_valid_chunk, player_input, rotate_player, solve_collisions, update_grounded, FallVelocity, Health, Player, PlayerSprite};
+use crate::GameState;
+
+pub const FPS: usize = 60;
+pub const FIXED_DELTA: f32 = 1.0 / FPS as f32;
+pub const MAX_PREDICTION_WINDOW: usize = 8;
+
+#[derive(Debug)]
+pub struct GgrsConfig;
+impl ggrs::Config for GgrsConfig {
+    type Input = PlayerInput;
+    type State = u8;
+    type Address = String;
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Eq, Pod, Zeroable, Debug, Default)]
+pub struct PlayerInput {
+    pub buttons: u8,
+}
+
+impl PlayerInput {
+    pub const LEFT: u8 = 1 << 0;
+    pub const RIGHT: u8 = 1 << 1;
+    pub const JUMP: u8 = 1 << 2;
+    pub const UP: u8 = 1 << 3;
+    pub const DOWN: u8 = 1 << 4;
+    pub const NOCLIP: u8 = 1 << 5;
+
+    pub fn pressed(&self, button: u8) -> bool {
+        self.buttons & button != 0
+    }
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct PlayerHandle(pub usize);
+
+#[derive(Resource, Clone)]
+pub struct SessionConfig {
+    pub num_players: usize,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self { num_players: 2 }
+    }
+}
+
+/// Maps each local GGRS player handle to the device that drives it, in spawn order.
+#[derive(Resource, Clone)]
+pub struct PlayerSources(pub Vec<Source>);
+
+impl Default for PlayerSources {
+    fn default() -> Self {
+        Self(vec![Source::KeyboardLeft])
+    }
+}
+
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SessionType {
+    #[default]
+    SyncTest,
+    P2P,
+}
+
+pub struct NetcodePlugin;
+impl Plugin for NetcodePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SessionConfig>();
+        app.init_resource::<SessionType>();
+        app.init_resource::<PlayerSources>();
+
+        app.add_plugins(GgrsPlugin::<GgrsConfig>::default());
+        app.set_rollback_schedule_fps(FPS);
+
+        app.rollback_component_with_copy::<Player>();
+        app.rollback_component_with_copy::<PlayerSprite>();
+        app.rollback_component_with_copy::<Position>();
+        app.rollback_component_with_copy::<LinearVelocity>();
+        app.rollback_component_with_copy::<Health>();
+        app.rollback_component_with_copy::<FallVelocity>();
+
+        app.add_systems(ReadInputs, read_local_inputs);
+
+        // solve_collisions stays in SubstepSchedule (as in the baseline) so it corrects once per
+        // substep, not once per tick; run_physics_step below drives that schedule from inside
+        // GgrsSchedule instead of xpbd's normal PostUpdate pass, so substep corrections happen
+        // against the replayed Position on a resimulation.
+        app.add_systems(
+            SubstepSchedule,
+            solve_collisions.in_set(SubstepSet::SolveUserConstraints).run_if(in_state(GameState::Game)).run_if(is_inside_valid_chunk),
+        );
+
+        // run_physics_step re-runs xpbd's PhysicsSchedule (and, through it, SubstepSchedule) here
+        // so Collisions/ShapeHits are recomputed against the replayed Position on a resimulation
+        // instead of read back stale from the last real-time step. App setup must stop
+        // PhysicsPlugins from also scheduling PhysicsSchedule into PostUpdate, or the narrow
+        // phase runs twice per real frame.
+        app.add_systems(
+            GgrsSchedule,
+            (
+                player_input,
+                apply_gravity,
+                run_physics_step,
+                update_grounded,
+                handle_fall_damage,
+                rotate_player,
+            )
+                .chain()
+                .run_if(in_state(GameState::Game))
+                .run_if(is_inside_valid_chunk),
+        );
+    }
+}
+
+fn run_physics_step(world: &mut World) {
+    world.run_schedule(PhysicsSchedule);
+}
+
+fn read_local_inputs(
+    mut commands: Commands,
+    input_state: Res<InputState>,
+    player_sources: Res<PlayerSources>,
+    local_players: Res<LocalPlayers>,
+) {
+    let mut local_inputs = HashMap::new();
+
+    for &handle in &local_players.0 {
+        let Some(&source) = player_sources.0.get(handle) else {
+            continue;
+        };
+
+        let mut buttons = 0u8;
+        if input_state.pressed(source, Key::Left) {
+            buttons |= PlayerInput::LEFT;
+        }
+        if input_state.pressed(source, Key::Right) {
+            buttons |= PlayerInput::RIGHT;
+        }
+        if input_state.pressed(source, Key::Jump) {
+            buttons |= PlayerInput::JUMP;
+        }
+        if input_state.pressed(source, Key::Up) {
+            buttons |= PlayerInput::UP;
+        }
+        if input_state.pressed(source, Key::Down) {
+            buttons |= PlayerInput::DOWN;
+        }
+        if input_state.pressed(source, Key::Noclip) {
+            buttons |= PlayerInput::NOCLIP;
+        }
+
+        local_inputs.insert(handle, PlayerInput { buttons });
+    }
+
+    commands.insert_resource(LocalInputs::<GgrsConfig>(local_inputs));
+}
+
+/// Callers still need to add each remote player/spectator to the builder (and bind a real
+/// socket) before the game enters `GameState::Game`.
+pub fn start_p2p_session(config: &SessionConfig) -> ggrs::SessionBuilder<GgrsConfig> {
+    ggrs::SessionBuilder::<GgrsConfig>::new()
+        .with_num_players(config.num_players)
+        .with_max_prediction_window(MAX_PREDICTION_WINDOW)
+        .expect("max prediction window should be valid")
+}
@@ -1,29 +1,75 @@
 use std::f32::consts::FRAC_PI_2;
 
 use bevy::prelude::*;
+use bevy_ggrs::PlayerInputs;
 use bevy_inspector_egui::quick::ResourceInspectorPlugin;
-use bevy_xpbd_2d::{components::{LinearVelocity, Position, RigidBody, Rotation}, math::Vector, plugins::{collision::{Collider, Collisions}, spatial_query::{ShapeCaster, ShapeHits}}, SubstepSchedule, SubstepSet};
+use bevy_xpbd_2d::{components::{LinearVelocity, Position, RigidBody, Rotation}, math::Vector, plugins::{collision::{Collider, Collisions}, spatial_query::{ShapeCaster, ShapeHits}}};
 
 use crate::{chunk::{Chunk, TILE_SIZE}, chunk_manager::UnloadChunks, utils::get_chunk_position, world::GameSystemSet, GameState};
+use crate::netcode::{GgrsConfig, PlayerHandle, PlayerInput, PlayerSources, FIXED_DELTA};
 use crate::utils::lerp;
 
 const PLAYER_SIZE: f32 = 28.0;
 const GRAVITY_ACCEL: f32 = 98.07;
 const TERMINAL_GRAVITY: f32 = 530.0;
+const FALL_DAMAGE_SAFE_SPEED: f32 = TERMINAL_GRAVITY * 0.5;
+const FALL_DAMAGE_SCALE: f32 = 0.1;
+const MAX_HEALTH: f32 = 100.0;
+const COYOTE_TIME: f32 = 0.1;
+const JUMP_BUFFER_TIME: f32 = 0.12;
+const SHORT_HOP_FACTOR: f32 = 0.5;
+const JUMP_MASK: u8 = PlayerInput::JUMP | PlayerInput::UP;
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy)]
 pub struct Player {
     pub is_on_ground: bool,
+    was_on_ground: bool,
     pub direction: i8,
-    pub noclip: bool
+    pub noclip: bool,
+    spawn_position: Vector,
+    coyote_timer: f32,
+    jump_buffer_timer: f32,
+    prev_buttons: u8,
+    damage_event_count: u32,
+    last_damage_amount: f32,
+    death_event_count: u32,
 }
 
-#[derive(Component)]
+/// Not rollback-tracked, so a resimulation can't rewind `*_seen` and re-fire an already-confirmed event.
+#[derive(Component, Default)]
+struct PlayerEventCursor {
+    damage_seen: u32,
+    death_seen: u32,
+}
+
+#[derive(Component, Clone, Copy)]
 struct PlayerSprite
 {
     pub rotation: f32
 }
 
+#[derive(Component, Clone, Copy)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+/// Latches `LinearVelocity.y` at the end of `apply_gravity`, before `solve_collisions` clamps it
+/// to zero on landing, so `handle_fall_damage` can read the impact speed that caused a landing.
+#[derive(Component, Clone, Copy, Default)]
+pub(crate) struct FallVelocity(f32);
+
+#[derive(Event)]
+pub struct PlayerDamaged {
+    pub entity: Entity,
+    pub amount: f32,
+}
+
+#[derive(Event)]
+pub struct PlayerDied {
+    pub entity: Entity,
+}
+
 #[derive(Resource, Default, Reflect)]
 #[reflect(Resource)]
 pub struct CurrentChunkPosition {
@@ -36,24 +82,15 @@ impl Plugin for PlayerPlugin {
         app.insert_resource(CurrentChunkPosition { position: IVec2::ZERO });
         app.register_type::<CurrentChunkPosition>();
         app.add_plugins(ResourceInspectorPlugin::<CurrentChunkPosition>::default());
+        app.add_event::<PlayerDamaged>();
+        app.add_event::<PlayerDied>();
         app.add_systems(OnEnter(GameState::Game), spawn_player.in_set(GameSystemSet::Player));
-        app.add_systems(Update, 
-            (
-                (player_input,
-                apply_gravity,
-                update_grounded,
-                rotate_player).run_if(is_inside_valid_chunk),
-                set_chunk_pos
-            ).chain().in_set(GameSystemSet::Player)
-        );
-        app.add_systems(
-            SubstepSchedule,
-            solve_collisions.in_set(SubstepSet::SolveUserConstraints).run_if(in_state(GameState::Game)).run_if(is_not_in_noclip).run_if(is_inside_valid_chunk),
-        );
+        app.add_systems(Update, set_chunk_pos.run_if(is_inside_valid_chunk).in_set(GameSystemSet::Player));
+        app.add_systems(Update, emit_player_events.in_set(GameSystemSet::Player));
     }
 }
 
-fn is_inside_valid_chunk(
+pub(crate) fn is_inside_valid_chunk(
     chunk_pos_res: Res<CurrentChunkPosition>,
     chunk_query: Query<&Chunk>
 ) -> bool
@@ -66,17 +103,22 @@ fn is_inside_valid_chunk(
     return false;
 }
 
-fn is_not_in_noclip(
-    player_query: Query<&Player>
-) -> bool{
-    return !player_query.get_single().unwrap().noclip;
-}
-
 fn spawn_player(
     mut commands: Commands,
-    mut unload_chunks_ev : EventWriter<UnloadChunks>
+    mut unload_chunks_ev : EventWriter<UnloadChunks>,
+    player_sources: Res<PlayerSources>
 ) {
+    for handle in 0..player_sources.0.len() {
+        spawn_single_player(&mut commands, handle);
+    }
+
+    unload_chunks_ev.send(UnloadChunks { force: true });
+}
+
+fn spawn_single_player(commands: &mut Commands, handle: usize) {
     let player_collider = Collider::rectangle(PLAYER_SIZE, PLAYER_SIZE);
+    let spawn_x = 16.0 + handle as f32 * TILE_SIZE as f32;
+    let spawn_position = Vector::new(spawn_x, 50.0);
 
     commands.spawn(
         (
@@ -90,10 +132,18 @@ fn spawn_player(
                     color: Color::rgba(1.0, 1.0, 1.0, 0.0),
                     ..default()
                 },
-                transform: Transform::from_xyz(16.0, 50.0, 1.0),
+                transform: Transform::from_xyz(spawn_x, 50.0, 1.0),
                 ..default()
             },
-            Player {is_on_ground: false, direction: 0, noclip: false }
+            Player {
+                is_on_ground: false, was_on_ground: false, direction: 0, noclip: false, spawn_position,
+                coyote_timer: 0.0, jump_buffer_timer: 0.0, prev_buttons: 0,
+                damage_event_count: 0, last_damage_amount: 0.0, death_event_count: 0
+            },
+            Health { current: MAX_HEALTH, max: MAX_HEALTH },
+            FallVelocity::default(),
+            PlayerEventCursor::default(),
+            PlayerHandle(handle)
         )
     ).with_children(|parent| {
         parent.spawn(
@@ -111,14 +161,14 @@ fn spawn_player(
             )
         );
     });
-
-    unload_chunks_ev.send(UnloadChunks { force: true });
 }
 
-fn update_grounded(
+pub(crate) fn update_grounded(
     mut player_query: Query<(&ShapeHits, &mut Player)>
 ) {
     for (hits, mut player) in player_query.iter_mut() {
+        let was_grounded = player.is_on_ground;
+
         player.is_on_ground = hits.iter().any(|hit| {
             if hit.normal1.y > 0.0 || hit.normal2.y > 0.0 {
                 true
@@ -126,31 +176,38 @@ fn update_grounded(
                 false
             }
         });
+
+        if was_grounded && !player.is_on_ground {
+            player.coyote_timer = COYOTE_TIME;
+        }
     }
 }
 
-fn rotate_player(
-    player_query: Query<&Player>,
+pub(crate) fn rotate_player(
+    player_query: Query<(&Player, &Children)>,
     mut player_sprite_query: Query<(&mut Transform, &mut PlayerSprite)>,
-    time: Res<Time>
 ) {
-    if let Ok((mut sprite_transform, mut player_sprite)) = player_sprite_query.get_single_mut() {
-        if let Ok(player) = player_query.get_single() {
+    for (player, children) in player_query.iter() {
+        for &child in children.iter() {
+            let Ok((mut sprite_transform, mut player_sprite)) = player_sprite_query.get_mut(child) else {
+                continue;
+            };
+
             if !player.is_on_ground {
-                player_sprite.rotation -= (9.6 * time.delta_seconds()) * player.direction as f32;
+                player_sprite.rotation -= (9.6 * FIXED_DELTA) * player.direction as f32;
             } else {
                 let nineties = (player_sprite.rotation / FRAC_PI_2).round() * FRAC_PI_2;
                 player_sprite.rotation = lerp(player_sprite.rotation, nineties, 0.25);
             }
-            
+
             sprite_transform.rotation = Quat::from_axis_angle(Vec3::Z, player_sprite.rotation);
         }
     }
 }
 
-fn solve_collisions(
+pub(crate) fn solve_collisions(
     collisions: Res<Collisions>,
-    mut player_query: Query<(&mut Position, &mut LinearVelocity), With<Player>>
+    mut player_query: Query<(&mut Position, &mut LinearVelocity, &Player)>
 ) {
     for contacts in collisions.iter() {
         if !contacts.during_current_substep {
@@ -158,7 +215,7 @@ fn solve_collisions(
         }
 
         let is_first: bool;
-        let (mut position, mut linear_velocity) = 
+        let (mut position, mut linear_velocity, player) =
             if let Ok(player) = player_query.get_mut(contacts.entity1) {
                 is_first = true;
                 player
@@ -169,6 +226,10 @@ fn solve_collisions(
                 continue;
             };
 
+        if player.noclip {
+            continue;
+        }
+
         for manifold in contacts.manifolds.iter() {
             let normal = if is_first {
                 -manifold.global_normal1(&Rotation::ZERO)
@@ -189,40 +250,98 @@ fn solve_collisions(
     }
 }
 
-fn apply_gravity(
-    mut player_query: Query<(&mut LinearVelocity, &Player)>,
-    time: Res<Time>
+pub(crate) fn apply_gravity(
+    mut player_query: Query<(&mut LinearVelocity, &Player, &mut FallVelocity)>,
 ) {
-    if let Ok((mut player_velocity, player)) = player_query.get_single_mut() {
+    for (mut player_velocity, player, mut fall_velocity) in player_query.iter_mut() {
         if !player.noclip {
             if !player.is_on_ground {
                 if player_velocity.y > -TERMINAL_GRAVITY {
-                    player_velocity.y -= (GRAVITY_ACCEL * TILE_SIZE as f32) * time.delta_seconds();
+                    player_velocity.y -= (GRAVITY_ACCEL * TILE_SIZE as f32) * FIXED_DELTA;
                 } else if player_velocity.y < -TERMINAL_GRAVITY {
                     player_velocity.y = -TERMINAL_GRAVITY;
                 }
             }
         }
+
+        // Latched here, before `solve_collisions` clamps `linear_velocity.y` to zero on landing.
+        fall_velocity.0 = player_velocity.y;
     }
 }
 
-fn player_input(
-    mut player_query: Query<(&mut LinearVelocity, &mut Player)>,
-    keyboard_input: Res<ButtonInput<KeyCode>>
+// Bumps the event counters instead of sending PlayerDamaged/PlayerDied directly: this runs
+// inside GgrsSchedule, which resimulates already-confirmed ticks and would otherwise re-fire
+// them. emit_player_events turns a counter change into a real event exactly once.
+pub(crate) fn handle_fall_damage(
+    mut player_query: Query<(&mut Position, &mut LinearVelocity, &mut Player, &FallVelocity, &mut Health)>,
 ) {
-    if let Ok((mut player_linear_velocity, mut player)) = player_query.get_single_mut() {
+    for (mut position, mut velocity, mut player, fall_velocity, mut health) in player_query.iter_mut() {
+        let just_landed = player.is_on_ground && !player.was_on_ground;
+        player.was_on_ground = player.is_on_ground;
+
+        if player.noclip || !just_landed {
+            continue;
+        }
+
+        let impact_speed = fall_velocity.0.abs();
+        if impact_speed <= FALL_DAMAGE_SAFE_SPEED {
+            continue;
+        }
+
+        let amount = (impact_speed - FALL_DAMAGE_SAFE_SPEED) * FALL_DAMAGE_SCALE;
+        health.current = (health.current - amount).max(0.0);
+        player.last_damage_amount = amount;
+        player.damage_event_count += 1;
+
+        if health.current <= 0.0 {
+            position.0 = player.spawn_position;
+            velocity.0 = Vector::ZERO;
+            player.is_on_ground = false;
+            player.was_on_ground = false;
+            player.coyote_timer = 0.0;
+            player.jump_buffer_timer = 0.0;
+            health.current = health.max;
+            player.death_event_count += 1;
+        }
+    }
+}
+
+fn emit_player_events(
+    mut player_query: Query<(Entity, &Player, &mut PlayerEventCursor)>,
+    mut damaged_ev: EventWriter<PlayerDamaged>,
+    mut died_ev: EventWriter<PlayerDied>,
+) {
+    for (entity, player, mut cursor) in player_query.iter_mut() {
+        if player.damage_event_count != cursor.damage_seen {
+            cursor.damage_seen = player.damage_event_count;
+            damaged_ev.send(PlayerDamaged { entity, amount: player.last_damage_amount });
+        }
+        if player.death_event_count != cursor.death_seen {
+            cursor.death_seen = player.death_event_count;
+            died_ev.send(PlayerDied { entity });
+        }
+    }
+}
+
+pub(crate) fn player_input(
+    mut player_query: Query<(&mut LinearVelocity, &mut Player, &PlayerHandle)>,
+    inputs: Res<PlayerInputs<GgrsConfig>>
+) {
+    for (mut player_linear_velocity, mut player, handle) in player_query.iter_mut() {
+        let (input, _) = inputs[handle.0];
         let speed: f32 = TILE_SIZE as f32 * 10.0;
         let jump_force = 16.0 * TILE_SIZE as f32;
 
-        if keyboard_input.just_pressed(KeyCode::KeyF) {
+        let noclip_just_pressed = input.pressed(PlayerInput::NOCLIP) && player.prev_buttons & PlayerInput::NOCLIP == 0;
+        if noclip_just_pressed {
             player.noclip = !player.noclip;
         }
-    
-        if keyboard_input.pressed(KeyCode::ArrowLeft) || keyboard_input.pressed(KeyCode::KeyA) {
+
+        if input.pressed(PlayerInput::LEFT) {
             player_linear_velocity.x = lerp(player_linear_velocity.x, -speed, 0.25);
             player.direction = -1;
         }
-        else if keyboard_input.pressed(KeyCode::ArrowRight) || keyboard_input.pressed(KeyCode::KeyD) {
+        else if input.pressed(PlayerInput::RIGHT) {
             player_linear_velocity.x = lerp(player_linear_velocity.x, speed, 0.25);
             player.direction = 1;
         } else {
@@ -232,23 +351,39 @@ fn player_input(
             }
         }
 
-        if keyboard_input.pressed(KeyCode::Space) || keyboard_input.pressed(KeyCode::KeyW) || keyboard_input.pressed(KeyCode::ArrowUp) {
-            if !player.noclip {
-                if player.is_on_ground {
-                    player_linear_velocity.y = jump_force;
-                }
+        let jump_held = input.buttons & JUMP_MASK != 0;
+        let jump_just_pressed = jump_held && player.prev_buttons & JUMP_MASK == 0;
+        let jump_released = !jump_held && player.prev_buttons & JUMP_MASK != 0;
+        if jump_just_pressed {
+            player.jump_buffer_timer = JUMP_BUFFER_TIME;
+        }
+
+        if !player.noclip {
+            let can_jump = player.is_on_ground || player.coyote_timer > 0.0;
+            if player.jump_buffer_timer > 0.0 && can_jump {
+                player_linear_velocity.y = jump_force;
+                player.jump_buffer_timer = 0.0;
+                player.coyote_timer = 0.0;
+            } else if jump_released && player_linear_velocity.y > 0.0 {
+                // Short-hop: releasing jump early cuts the ascent instead of always reaching full height.
+                player_linear_velocity.y *= SHORT_HOP_FACTOR;
             }
+
+            player.jump_buffer_timer = (player.jump_buffer_timer - FIXED_DELTA).max(0.0);
+            player.coyote_timer = (player.coyote_timer - FIXED_DELTA).max(0.0);
         }
 
         if player.noclip {
-            if keyboard_input.pressed(KeyCode::KeyS) || keyboard_input.pressed(KeyCode::ArrowDown) {
+            if input.pressed(PlayerInput::DOWN) {
                 player_linear_velocity.y = lerp(player_linear_velocity.y, -speed, 0.25);
-            } else if keyboard_input.pressed(KeyCode::Space) || keyboard_input.pressed(KeyCode::KeyW) || keyboard_input.pressed(KeyCode::ArrowUp) {
+            } else if jump_held {
                 player_linear_velocity.y = lerp(player_linear_velocity.y, speed, 0.25);
             } else {
                 player_linear_velocity.y = lerp(player_linear_velocity.y, 0.0, 0.25);
             }
         }
+
+        player.prev_buttons = input.buttons;
     }
 }
 
@@ -257,8 +392,10 @@ fn set_chunk_pos(
     mut unload_chunks_ev : EventWriter<UnloadChunks>,
     mut chunk_pos_res: ResMut<CurrentChunkPosition>
 ) {
-    let player_transform = player_query.get_single().unwrap();
-    
+    let Some(player_transform) = player_query.iter().next() else {
+        return;
+    };
+
     let player_pos_in_pixels = player_transform.translation.xy().floor();
     let player_position = IVec2::new((player_pos_in_pixels.x / TILE_SIZE as f32).floor() as i32, (player_pos_in_pixels.y / TILE_SIZE as f32).floor() as i32);
     if chunk_pos_res.position != get_chunk_position(player_position) {